@@ -0,0 +1,240 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use axum::body::{self, Body};
+use axum::extract::{Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Json, Response};
+use serde_json::{json, Value};
+
+use crate::config::CaptureConfig;
+use crate::error::ApiError;
+use crate::request_id;
+use crate::AppState;
+
+const CAPTURED_HEADERS: &[&str] = &[
+    "host",
+    "user-agent",
+    "content-type",
+    "content-length",
+    "accept",
+    "x-forwarded-for",
+];
+
+/// Ring buffer of the last `size` requests, keeping the first `body_bytes`
+/// of each body.
+#[derive(Clone)]
+pub struct RecentRequests {
+    capacity: usize,
+    body_bytes: usize,
+    admin_token: Arc<str>,
+    entries: Arc<Mutex<VecDeque<Value>>>,
+}
+
+impl RecentRequests {
+    pub fn new(config: &CaptureConfig) -> Self {
+        Self {
+            capacity: config.size,
+            body_bytes: config.body_bytes,
+            admin_token: config.admin_token.as_deref().unwrap_or("").into(),
+            entries: Arc::new(Mutex::new(VecDeque::with_capacity(config.size))),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    fn push(&self, entry: Value) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+}
+
+pub async fn capture(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let recent = state.recent;
+    if recent.capacity == 0 || req.uri().path().starts_with("/admin/") {
+        return next.run(req).await;
+    }
+
+    let (parts, body) = req.into_parts();
+    // Bounded by the body limit layer outside this middleware.
+    let bytes = match body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(err) => return ApiError::from_body_error(err).into_response(),
+    };
+
+    let headers: serde_json::Map<String, Value> = CAPTURED_HEADERS
+        .iter()
+        .filter_map(|name| {
+            let value = parts.headers.get(*name)?.to_str().ok()?;
+            Some((name.to_string(), Value::from(value)))
+        })
+        .collect();
+    let truncated = &bytes[..bytes.len().min(recent.body_bytes)];
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let mut entry = json!({
+        "timestamp_ms": timestamp,
+        "request_id": request_id::current(),
+        "method": parts.method.as_str(),
+        "path": parts.uri.path_and_query().map_or("/", |pq| pq.as_str()),
+        "headers": headers,
+        "body": String::from_utf8_lossy(truncated),
+        "body_truncated": truncated.len() < bytes.len(),
+    });
+
+    let started = Instant::now();
+    let response = next.run(Request::from_parts(parts, Body::from(bytes))).await;
+
+    entry["status"] = response.status().as_u16().into();
+    entry["latency_ms"] = (started.elapsed().as_secs_f64() * 1000.0).into();
+    recent.push(entry);
+    response
+}
+
+/// Guards the admin routes with `Authorization: Bearer <admin_token>`.
+pub async fn authorize(State(recent): State<RecentRequests>, req: Request, next: Next) -> Response {
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    match token {
+        Some(token) if constant_time_eq(token.as_bytes(), recent.admin_token.as_bytes()) => {
+            next.run(req).await
+        }
+        _ => ApiError::Unauthorized.into_response(),
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub async fn list(State(state): State<AppState>) -> Json<Value> {
+    let entries = state.recent.entries.lock().unwrap();
+    Json(json!({
+        "capacity": state.recent.capacity,
+        "requests": entries.iter().rev().collect::<Vec<_>>(),
+        "request_id": request_id::current(),
+    }))
+}
+
+pub async fn clear(State(state): State<AppState>) -> StatusCode {
+    state.recent.entries.lock().unwrap().clear();
+    StatusCode::NO_CONTENT
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::routing::get;
+    use axum::Router;
+    use tower::Service;
+
+    use super::*;
+    use crate::config::Config;
+    use crate::health::Health;
+    use crate::manifest::Manifest;
+
+    fn recent(size: usize) -> RecentRequests {
+        RecentRequests::new(&CaptureConfig {
+            size,
+            body_bytes: 4,
+            admin_token: Some("s3cret".to_string()),
+        })
+    }
+
+    fn paths(recent: &RecentRequests) -> Vec<String> {
+        let entries = recent.entries.lock().unwrap();
+        entries.iter().map(|e| e["path"].as_str().unwrap().to_string()).collect()
+    }
+
+    #[test]
+    fn constant_time_eq_compares_contents_and_length() {
+        assert!(constant_time_eq(b"s3cret", b"s3cret"));
+        assert!(!constant_time_eq(b"s3cret", b"s3creT"));
+        assert!(!constant_time_eq(b"s3cret", b"s3cre"));
+        assert!(!constant_time_eq(b"", b"s3cret"));
+        assert!(constant_time_eq(b"", b""));
+    }
+
+    #[test]
+    fn evicts_the_oldest_entry_at_capacity() {
+        let recent = recent(2);
+        for path in ["/a", "/b", "/c"] {
+            recent.push(json!({ "path": path }));
+        }
+        assert_eq!(paths(&recent), ["/b", "/c"]);
+    }
+
+    #[test]
+    fn stores_nothing_when_disabled() {
+        let recent = recent(0);
+        recent.push(json!({ "path": "/a" }));
+        assert!(paths(&recent).is_empty());
+        assert!(!recent.enabled());
+    }
+
+    async fn status(router: &mut Router, authorization: Option<&str>) -> StatusCode {
+        let mut req = Request::get("/admin/requests");
+        if let Some(value) = authorization {
+            req = req.header(header::AUTHORIZATION, value);
+        }
+        router.call(req.body(Body::empty()).unwrap()).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn authorize_requires_the_bearer_token() {
+        let recent = recent(1);
+        let mut router = Router::new().route(
+            "/admin/requests",
+            get(|| async { "ok" })
+                .route_layer(axum::middleware::from_fn_with_state(recent, authorize)),
+        );
+
+        assert_eq!(status(&mut router, None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(&mut router, Some("Bearer wrong")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(&mut router, Some("s3cret")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(&mut router, Some("Basic s3cret")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(&mut router, Some("Bearer s3cret")).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn records_path_and_query_for_absolute_uris() {
+        let state = AppState {
+            manifest: Arc::new(Manifest::new(&Config::default())),
+            health: Arc::new(Health::new(vec![], vec![])),
+            recent: recent(4),
+            proxy: None,
+        };
+        let mut router = Router::new()
+            .route("/healthz", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(state.clone(), capture))
+            .with_state(state.clone());
+
+        // HTTP/2 requests arrive with an absolute URI.
+        let req = Request::get("http://localhost:18092/healthz?verbose=1")
+            .body(Body::from("abcdef"))
+            .unwrap();
+        router.call(req).await.unwrap();
+
+        let entries = state.recent.entries.lock().unwrap();
+        assert_eq!(entries[0]["path"], "/healthz?verbose=1");
+        assert_eq!(entries[0]["body"], "abcd");
+        assert_eq!(entries[0]["body_truncated"], true);
+        assert_eq!(entries[0]["status"], 200);
+    }
+}
@@ -0,0 +1,619 @@
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use axum::http::{HeaderName, HeaderValue, Method, Uri};
+use serde::Deserialize;
+
+/// Server configuration, layered as: defaults, then the optional
+/// `--config <path>` TOML file, then env vars.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub port: u16,
+    pub instance_id: Option<String>,
+    pub log_format: String,
+    pub crash_report_dir: Option<PathBuf>,
+    pub max_body_bytes: usize,
+    pub compression: bool,
+    pub server: ServerConfig,
+    pub shutdown: ShutdownConfig,
+    pub request_capture: CaptureConfig,
+    pub rate_limit: Option<RateLimitConfig>,
+    pub tls: Option<TlsConfig>,
+    pub security_headers: SecurityHeadersConfig,
+    pub cors: CorsConfig,
+    pub proxy: ProxyConfig,
+}
+
+/// Connection-level tuning. HTTP/2 is served by prior knowledge on plain
+/// listeners and negotiated via ALPN under TLS.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub http2: bool,
+    pub tcp_nodelay: bool,
+    pub keep_alive: bool,
+    pub header_read_timeout_secs: u64,
+    pub http2_max_concurrent_streams: u32,
+    pub http2_keep_alive_interval_secs: Option<u64>,
+    pub http2_keep_alive_timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShutdownConfig {
+    pub grace_secs: u64,
+    pub drain_secs: u64,
+}
+
+/// Capture is off while `size` is 0; enabling it requires `admin_token`,
+/// which `/admin/requests` expects as a bearer token.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CaptureConfig {
+    pub size: usize,
+    pub body_bytes: usize,
+    pub admin_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub rps: f64,
+    pub burst: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert_path: String,
+    pub key_path: String,
+}
+
+/// An empty string disables the header.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SecurityHeadersConfig {
+    pub hsts: String,
+    pub content_type_options: String,
+    pub referrer_policy: String,
+    pub frame_options: String,
+    pub csp: String,
+}
+
+/// CORS is disabled while `allowed_origins` is empty.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    pub expose_headers: Vec<String>,
+    pub allow_credentials: bool,
+    pub max_age_secs: Option<u64>,
+}
+
+/// Gateway mode: `/api/*` is proxied to `upstreams` while the list is non-empty.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProxyConfig {
+    pub upstreams: Vec<String>,
+    pub health_path: String,
+    pub health_interval_secs: u64,
+    pub timeout_secs: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            port: 8080,
+            instance_id: None,
+            log_format: "pretty".to_string(),
+            crash_report_dir: None,
+            max_body_bytes: 2 * 1024 * 1024,
+            compression: true,
+            server: ServerConfig::default(),
+            shutdown: ShutdownConfig::default(),
+            request_capture: CaptureConfig::default(),
+            rate_limit: None,
+            tls: None,
+            security_headers: SecurityHeadersConfig::default(),
+            cors: CorsConfig::default(),
+            proxy: ProxyConfig::default(),
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            http2: true,
+            tcp_nodelay: true,
+            keep_alive: true,
+            header_read_timeout_secs: 30,
+            http2_max_concurrent_streams: 200,
+            http2_keep_alive_interval_secs: None,
+            http2_keep_alive_timeout_secs: 20,
+        }
+    }
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            grace_secs: 5,
+            drain_secs: 30,
+        }
+    }
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            size: 0,
+            body_bytes: 1024,
+            admin_token: None,
+        }
+    }
+}
+
+impl Default for SecurityHeadersConfig {
+    fn default() -> Self {
+        Self {
+            hsts: String::new(),
+            content_type_options: "nosniff".to_string(),
+            referrer_policy: "no-referrer".to_string(),
+            frame_options: "DENY".to_string(),
+            csp: "default-src 'none'; frame-ancestors 'none'".to_string(),
+        }
+    }
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        let strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+        Self {
+            allowed_origins: Vec::new(),
+            allowed_methods: strings(&["GET", "POST", "PUT", "PATCH", "DELETE"]),
+            allowed_headers: strings(&["content-type", "authorization", "x-request-id"]),
+            expose_headers: strings(&["x-request-id", "retry-after"]),
+            allow_credentials: false,
+            max_age_secs: None,
+        }
+    }
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self {
+            upstreams: Vec::new(),
+            health_path: "/health".to_string(),
+            health_interval_secs: 5,
+            timeout_secs: 30,
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Self {
+        let mut config = match config_path() {
+            Some(path) => Self::from_file(&path),
+            None => Self::default(),
+        };
+        config.apply_env();
+        config.validate();
+        config
+    }
+
+    pub fn instance_id(&self) -> String {
+        self.instance_id
+            .clone()
+            .unwrap_or_else(|| format!("dumb-server-{}", self.port))
+    }
+
+    fn from_file(path: &Path) -> Self {
+        let contents = fs::read_to_string(path)
+            .unwrap_or_else(|err| panic!("Failed to read config {}: {}", path.display(), err));
+        toml::from_str(&contents).unwrap_or_else(|err| panic!("Invalid config {}: {}", path.display(), err))
+    }
+
+    fn apply_env(&mut self) {
+        set_from_env("PORT", &mut self.port);
+        set_opt_from_env("INSTANCE_ID", &mut self.instance_id);
+        set_from_env("LOG_FORMAT", &mut self.log_format);
+        set_opt_from_env("CRASH_REPORT_DIR", &mut self.crash_report_dir);
+        set_from_env("MAX_BODY_BYTES", &mut self.max_body_bytes);
+        set_from_env("COMPRESSION", &mut self.compression);
+
+        let server = &mut self.server;
+        set_from_env("SERVER_HTTP2", &mut server.http2);
+        set_from_env("SERVER_TCP_NODELAY", &mut server.tcp_nodelay);
+        set_from_env("SERVER_KEEP_ALIVE", &mut server.keep_alive);
+        set_from_env("SERVER_HEADER_READ_TIMEOUT_SECS", &mut server.header_read_timeout_secs);
+        set_from_env("SERVER_HTTP2_MAX_CONCURRENT_STREAMS", &mut server.http2_max_concurrent_streams);
+        set_opt_from_env("SERVER_HTTP2_KEEP_ALIVE_INTERVAL_SECS", &mut server.http2_keep_alive_interval_secs);
+        set_from_env("SERVER_HTTP2_KEEP_ALIVE_TIMEOUT_SECS", &mut server.http2_keep_alive_timeout_secs);
+
+        set_from_env("SHUTDOWN_GRACE_SECS", &mut self.shutdown.grace_secs);
+        set_from_env("SHUTDOWN_DRAIN_SECS", &mut self.shutdown.drain_secs);
+
+        set_from_env("REQUEST_CAPTURE_SIZE", &mut self.request_capture.size);
+        set_from_env("REQUEST_CAPTURE_BODY_BYTES", &mut self.request_capture.body_bytes);
+        set_opt_from_env("REQUEST_CAPTURE_ADMIN_TOKEN", &mut self.request_capture.admin_token);
+
+        if let Some(rps) = parse_env("RATE_LIMIT_RPS") {
+            let burst = self.rate_limit.as_ref().and_then(|limit| limit.burst);
+            self.rate_limit = Some(RateLimitConfig { rps, burst });
+        }
+        if let Some(limit) = self.rate_limit.as_mut() {
+            set_opt_from_env("RATE_LIMIT_BURST", &mut limit.burst);
+        }
+
+        match (parse_env("TLS_CERT_PATH"), parse_env("TLS_KEY_PATH")) {
+            (Some(cert_path), Some(key_path)) => self.tls = Some(TlsConfig { cert_path, key_path }),
+            (None, None) => {}
+            _ => panic!("TLS_CERT_PATH and TLS_KEY_PATH must be set together"),
+        }
+
+        let headers = &mut self.security_headers;
+        set_from_env("SECURITY_HSTS", &mut headers.hsts);
+        set_from_env("SECURITY_CONTENT_TYPE_OPTIONS", &mut headers.content_type_options);
+        set_from_env("SECURITY_REFERRER_POLICY", &mut headers.referrer_policy);
+        set_from_env("SECURITY_FRAME_OPTIONS", &mut headers.frame_options);
+        set_from_env("SECURITY_CSP", &mut headers.csp);
+
+        let cors = &mut self.cors;
+        set_list_from_env("CORS_ALLOWED_ORIGINS", &mut cors.allowed_origins);
+        set_list_from_env("CORS_ALLOWED_METHODS", &mut cors.allowed_methods);
+        set_list_from_env("CORS_ALLOWED_HEADERS", &mut cors.allowed_headers);
+        set_list_from_env("CORS_EXPOSE_HEADERS", &mut cors.expose_headers);
+        set_from_env("CORS_ALLOW_CREDENTIALS", &mut cors.allow_credentials);
+        set_opt_from_env("CORS_MAX_AGE_SECS", &mut cors.max_age_secs);
+
+        let proxy = &mut self.proxy;
+        set_list_from_env("PROXY_UPSTREAMS", &mut proxy.upstreams);
+        set_from_env("PROXY_HEALTH_PATH", &mut proxy.health_path);
+        set_from_env("PROXY_HEALTH_INTERVAL_SECS", &mut proxy.health_interval_secs);
+        set_from_env("PROXY_TIMEOUT_SECS", &mut proxy.timeout_secs);
+    }
+
+    /// Rejects values that would otherwise fail later, after the crash hook
+    /// is installed. Messages name the env var; the file key is checked too.
+    fn validate(&self) {
+        let capture = &self.request_capture;
+        if capture.size > 0 && capture.admin_token.as_deref().unwrap_or("").is_empty() {
+            panic!("REQUEST_CAPTURE_ADMIN_TOKEN is required when REQUEST_CAPTURE_SIZE is above 0");
+        }
+        if let Some(limit) = &self.rate_limit {
+            if limit.rps.is_nan() || limit.rps <= 0.0 {
+                panic!("Invalid RATE_LIMIT_RPS: must be greater than 0, got {}", limit.rps);
+            }
+            if let Some(burst) = limit.burst {
+                if burst.is_nan() || burst < 1.0 {
+                    panic!("Invalid RATE_LIMIT_BURST: must be at least 1, got {}", burst);
+                }
+            }
+        }
+
+        let proxy = &self.proxy;
+        for upstream in &proxy.upstreams {
+            let uri: Uri = upstream
+                .parse()
+                .unwrap_or_else(|err| panic!("Invalid PROXY_UPSTREAMS entry {}: {}", upstream, err));
+            if uri.scheme_str() != Some("http") || uri.authority().is_none() {
+                panic!("Invalid PROXY_UPSTREAMS entry {}: must be an http://host:port URL", upstream);
+            }
+        }
+        if !proxy.upstreams.is_empty() {
+            if proxy.health_interval_secs == 0 {
+                panic!("Invalid PROXY_HEALTH_INTERVAL_SECS: must be greater than 0");
+            }
+            if proxy.timeout_secs == 0 {
+                panic!("Invalid PROXY_TIMEOUT_SECS: must be greater than 0");
+            }
+        }
+
+        if let Some(tls) = &self.tls {
+            for (name, path) in [("TLS_CERT_PATH", &tls.cert_path), ("TLS_KEY_PATH", &tls.key_path)] {
+                if let Err(err) = fs::File::open(path) {
+                    panic!("Invalid {} {}: {}", name, path, err);
+                }
+            }
+        }
+
+        let headers = &self.security_headers;
+        for (name, value) in [
+            ("SECURITY_HSTS", &headers.hsts),
+            ("SECURITY_CONTENT_TYPE_OPTIONS", &headers.content_type_options),
+            ("SECURITY_REFERRER_POLICY", &headers.referrer_policy),
+            ("SECURITY_FRAME_OPTIONS", &headers.frame_options),
+            ("SECURITY_CSP", &headers.csp),
+        ] {
+            if HeaderValue::from_str(value).is_err() {
+                panic!("Invalid {}: not a valid header value: {:?}", name, value);
+            }
+        }
+
+        let cors = &self.cors;
+        if cors.allowed_origins.iter().any(|o| o == "*") {
+            if cors.allow_credentials {
+                panic!("Invalid CORS_ALLOW_CREDENTIALS: cannot be used with CORS_ALLOWED_ORIGINS=*");
+            }
+        } else {
+            check_each::<HeaderValue>("CORS_ALLOWED_ORIGINS", &cors.allowed_origins);
+        }
+        check_each::<Method>("CORS_ALLOWED_METHODS", &cors.allowed_methods);
+        check_each::<HeaderName>("CORS_ALLOWED_HEADERS", &cors.allowed_headers);
+        check_each::<HeaderName>("CORS_EXPOSE_HEADERS", &cors.expose_headers);
+    }
+}
+
+/// Panics naming `name` on the first entry that does not parse as `T`.
+fn check_each<T: FromStr>(name: &str, items: &[String]) {
+    for item in items {
+        if item.parse::<T>().is_err() {
+            panic!("Invalid {} entry: {:?}", name, item);
+        }
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    let mut args = env::args().skip(1);
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        if let Some(value) = arg.strip_prefix("--config=") {
+            path = Some(PathBuf::from(value));
+        } else if arg == "--config" {
+            path = Some(PathBuf::from(args.next().expect("--config requires a path")));
+        } else {
+            panic!("Unknown argument: {}", arg);
+        }
+    }
+    path
+}
+
+fn parse_env<T: FromStr>(name: &str) -> Option<T>
+where
+    T::Err: Display,
+{
+    let value = env::var(name).ok()?;
+    Some(
+        value
+            .parse()
+            .unwrap_or_else(|err| panic!("Invalid {}: {}", name, err)),
+    )
+}
+
+fn set_from_env<T: FromStr>(name: &str, field: &mut T)
+where
+    T::Err: Display,
+{
+    if let Some(value) = parse_env(name) {
+        *field = value;
+    }
+}
+
+fn set_opt_from_env<T: FromStr>(name: &str, field: &mut Option<T>)
+where
+    T::Err: Display,
+{
+    if let Some(value) = parse_env(name) {
+        *field = Some(value);
+    }
+}
+
+/// Comma-separated list; blank entries are dropped.
+fn set_list_from_env(name: &str, field: &mut Vec<String>) {
+    if let Ok(value) = env::var(name) {
+        *field = value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(String::from)
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_config(name: &str, contents: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("dumb-server-{}-{}.toml", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn file_values_override_defaults() {
+        let path = write_config(
+            "file",
+            "port = 9000\n[shutdown]\ngrace_secs = 1\n[proxy]\nupstreams = [\"http://a:1\"]\n",
+        );
+        let config = Config::from_file(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.port, 9000);
+        assert_eq!(config.shutdown.grace_secs, 1);
+        assert_eq!(config.shutdown.drain_secs, ShutdownConfig::default().drain_secs);
+        assert_eq!(config.proxy.upstreams, ["http://a:1"]);
+        assert_eq!(config.proxy.health_path, "/health");
+        assert_eq!(config.instance_id(), "dumb-server-9000");
+    }
+
+    // The only test that touches the process environment, so it cannot race.
+    #[test]
+    fn env_overrides_file() {
+        let path = write_config(
+            "env",
+            "port = 9000\ncompression = false\n[rate_limit]\nrps = 5\nburst = 10\n",
+        );
+        let mut config = Config::from_file(&path);
+        fs::remove_file(&path).unwrap();
+
+        let vars = [
+            ("PORT", "9100"),
+            ("RATE_LIMIT_RPS", "7"),
+            ("CORS_ALLOWED_ORIGINS", "https://a.example, ,https://b.example"),
+            ("TLS_CERT_PATH", "cert.pem"),
+            ("TLS_KEY_PATH", "key.pem"),
+        ];
+        for (name, value) in vars {
+            env::set_var(name, value);
+        }
+        config.apply_env();
+        for (name, _) in vars {
+            env::remove_var(name);
+        }
+
+        assert_eq!(config.port, 9100);
+        assert!(!config.compression, "file value kept when no env var is set");
+        let limit = config.rate_limit.unwrap();
+        assert_eq!((limit.rps, limit.burst), (7.0, Some(10.0)));
+        assert_eq!(config.cors.allowed_origins, ["https://a.example", "https://b.example"]);
+        let tls = config.tls.unwrap();
+        assert_eq!((tls.cert_path.as_str(), tls.key_path.as_str()), ("cert.pem", "key.pem"));
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown field")]
+    fn rejects_unknown_keys() {
+        let path = write_config("unknown", "prot = 9000\n");
+        let result = std::panic::catch_unwind(|| Config::from_file(&path));
+        fs::remove_file(&path).unwrap();
+        std::panic::resume_unwind(result.unwrap_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid RATE_LIMIT_RPS")]
+    fn rejects_non_positive_rps() {
+        let config = Config {
+            rate_limit: Some(RateLimitConfig { rps: 0.0, burst: None }),
+            ..Config::default()
+        };
+        config.validate();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid RATE_LIMIT_BURST")]
+    fn rejects_burst_below_one() {
+        let config = Config {
+            rate_limit: Some(RateLimitConfig { rps: 1.0, burst: Some(0.5) }),
+            ..Config::default()
+        };
+        config.validate();
+    }
+
+    fn with_upstream(proxy: ProxyConfig) -> Config {
+        Config {
+            proxy: ProxyConfig {
+                upstreams: vec!["http://127.0.0.1:1".to_string()],
+                ..proxy
+            },
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid PROXY_HEALTH_INTERVAL_SECS")]
+    fn rejects_zero_health_interval() {
+        with_upstream(ProxyConfig {
+            health_interval_secs: 0,
+            ..ProxyConfig::default()
+        })
+        .validate();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid PROXY_TIMEOUT_SECS")]
+    fn rejects_zero_proxy_timeout() {
+        with_upstream(ProxyConfig {
+            timeout_secs: 0,
+            ..ProxyConfig::default()
+        })
+        .validate();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid PROXY_UPSTREAMS entry https://x:1")]
+    fn rejects_https_upstreams() {
+        let mut config = with_upstream(ProxyConfig::default());
+        config.proxy.upstreams.push("https://x:1".to_string());
+        config.validate();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid PROXY_UPSTREAMS entry /relative")]
+    fn rejects_upstreams_without_authority() {
+        let mut config = with_upstream(ProxyConfig::default());
+        config.proxy.upstreams.push("/relative".to_string());
+        config.validate();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid CORS_ALLOW_CREDENTIALS")]
+    fn rejects_wildcard_origin_with_credentials() {
+        let config = Config {
+            cors: CorsConfig {
+                allowed_origins: vec!["*".to_string()],
+                allow_credentials: true,
+                ..CorsConfig::default()
+            },
+            ..Config::default()
+        };
+        config.validate();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid CORS_ALLOWED_METHODS entry: \"GE T\"")]
+    fn rejects_invalid_cors_methods() {
+        let config = Config {
+            cors: CorsConfig {
+                allowed_methods: vec!["GET".to_string(), "GE T".to_string()],
+                ..CorsConfig::default()
+            },
+            ..Config::default()
+        };
+        config.validate();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid SECURITY_CSP")]
+    fn rejects_invalid_security_header_values() {
+        let config = Config {
+            security_headers: SecurityHeadersConfig {
+                csp: "default-src 'none'\n".to_string(),
+                ..SecurityHeadersConfig::default()
+            },
+            ..Config::default()
+        };
+        config.validate();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid TLS_KEY_PATH /nonexistent/key.pem")]
+    fn rejects_unreadable_tls_files() {
+        let cert = write_config("cert", "");
+        let config = Config {
+            tls: Some(TlsConfig {
+                cert_path: cert.display().to_string(),
+                key_path: "/nonexistent/key.pem".to_string(),
+            }),
+            ..Config::default()
+        };
+        let result = std::panic::catch_unwind(|| config.validate());
+        fs::remove_file(&cert).unwrap();
+        std::panic::resume_unwind(result.unwrap_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "REQUEST_CAPTURE_ADMIN_TOKEN is required")]
+    fn capture_requires_an_admin_token() {
+        let mut config = Config::default();
+        config.request_capture.size = 10;
+        config.validate();
+    }
+}
@@ -0,0 +1,39 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use axum::http::{HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::config::CorsConfig;
+
+/// Builds the CORS layer, or `None` when no origins are allowed.
+/// An origin of `*` allows any origin. Entries are checked by `Config`.
+pub fn layer(config: &CorsConfig) -> Option<CorsLayer> {
+    if config.allowed_origins.is_empty() {
+        return None;
+    }
+
+    let origins = if config.allowed_origins.iter().any(|o| o == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(parse_all::<HeaderValue>(&config.allowed_origins))
+    };
+
+    let mut layer = CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(parse_all::<Method>(&config.allowed_methods))
+        .allow_headers(parse_all::<HeaderName>(&config.allowed_headers))
+        .expose_headers(parse_all::<HeaderName>(&config.expose_headers))
+        .allow_credentials(config.allow_credentials);
+    if let Some(secs) = config.max_age_secs {
+        layer = layer.max_age(Duration::from_secs(secs));
+    }
+    Some(layer)
+}
+
+fn parse_all<T: FromStr>(items: &[String]) -> Vec<T> {
+    items
+        .iter()
+        .map(|item| item.parse().ok().expect("CORS entry checked by Config::validate"))
+        .collect()
+}
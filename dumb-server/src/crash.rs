@@ -0,0 +1,112 @@
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fs, panic};
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde_json::json;
+
+use crate::error::ApiError;
+use crate::request_id;
+
+static PANICS: AtomicU64 = AtomicU64::new(0);
+
+tokio::task_local! {
+    static REQUEST: RequestContext;
+}
+
+#[derive(Clone)]
+struct RequestContext {
+    method: String,
+    uri: String,
+}
+
+/// Installs a panic hook that writes a JSON crash report into `dir`
+/// (default: `$TMPDIR/dumb-server-crashes`) before handing off to the
+/// default hook.
+pub fn install_hook(dir: Option<PathBuf>) {
+    let dir = dir.unwrap_or_else(|| env::temp_dir().join("dumb-server-crashes"));
+    let default_hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        let count = PANICS.fetch_add(1, Ordering::Relaxed) + 1;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let request = REQUEST
+            .try_with(|ctx| json!({"id": request_id::current(), "method": ctx.method, "uri": ctx.uri}))
+            .unwrap_or(serde_json::Value::Null);
+
+        let report = json!({
+            "timestamp_ms": timestamp,
+            "panic_count": count,
+            "message": panic_message(info.payload()),
+            "location": info.location().map(|l| l.to_string()),
+            "thread": std::thread::current().name(),
+            "request": request,
+            "build": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+                "debug": cfg!(debug_assertions),
+            },
+            "backtrace": Backtrace::force_capture().to_string(),
+        });
+
+        let path = dir.join(format!("crash-{}-{}.json", timestamp, count));
+        let written = fs::create_dir_all(&dir)
+            .and_then(|_| fs::write(&path, serde_json::to_vec_pretty(&report).unwrap_or_default()));
+        match written {
+            Ok(()) => tracing::error!(path = %path.display(), "Crash report written"),
+            Err(err) => tracing::error!(path = %path.display(), %err, "Failed to write crash report"),
+        }
+
+        default_hook(info);
+    }));
+}
+
+/// Makes the current request visible to the panic hook.
+pub async fn track_request(req: Request, next: Next) -> Response {
+    let ctx = RequestContext {
+        method: req.method().to_string(),
+        uri: req.uri().to_string(),
+    };
+    REQUEST.scope(ctx, next.run(req)).await
+}
+
+/// Converts a caught handler panic into a problem+json 500, carrying the
+/// usual `ApiError` members as extensions.
+pub fn panic_response(_err: Box<dyn Any + Send + 'static>) -> Response<Body> {
+    let err = ApiError::Internal;
+    let body = json!({
+        "type": "about:blank",
+        "title": "Internal Server Error",
+        "status": 500,
+        "detail": "The request handler panicked",
+        "error": err.message(),
+        "code": err.code(),
+        "request_id": request_id::current(),
+    });
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        [(header::CONTENT_TYPE, "application/problem+json")],
+        body.to_string(),
+    )
+        .into_response()
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s
+    } else {
+        "Box<dyn Any>"
+    }
+}
@@ -0,0 +1,140 @@
+use std::error::Error as _;
+
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Json, Response};
+use http_body_util::LengthLimitError;
+use serde_json::json;
+
+use crate::proxy::Proxied;
+use crate::request_id;
+
+/// Every error response is rendered as `{error, code, request_id}`.
+#[derive(Debug)]
+pub enum ApiError {
+    BadRequest(&'static str),
+    NotFound,
+    MethodNotAllowed,
+    PayloadTooLarge,
+    Unauthorized,
+    RateLimited { retry_after: u64 },
+    Internal,
+    BadGateway,
+    GatewayTimeout,
+    NoHealthyUpstream,
+}
+
+impl ApiError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::NotFound => StatusCode::NOT_FOUND,
+            ApiError::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            ApiError::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::BadGateway => StatusCode::BAD_GATEWAY,
+            ApiError::GatewayTimeout => StatusCode::GATEWAY_TIMEOUT,
+            ApiError::NoHealthyUpstream => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::NotFound => "not_found",
+            ApiError::MethodNotAllowed => "method_not_allowed",
+            ApiError::PayloadTooLarge => "payload_too_large",
+            ApiError::Unauthorized => "unauthorized",
+            ApiError::RateLimited { .. } => "rate_limited",
+            ApiError::Internal => "internal",
+            ApiError::BadGateway => "bad_gateway",
+            ApiError::GatewayTimeout => "gateway_timeout",
+            ApiError::NoHealthyUpstream => "no_healthy_upstream",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ApiError::BadRequest(message) => message.to_string(),
+            ApiError::NotFound => "no route matches this path".to_string(),
+            ApiError::MethodNotAllowed => "method not allowed on this route".to_string(),
+            ApiError::PayloadTooLarge => "request body is too large".to_string(),
+            ApiError::Unauthorized => "missing or invalid bearer token".to_string(),
+            ApiError::RateLimited { retry_after } => {
+                format!("rate limit exceeded, retry after {}s", retry_after)
+            }
+            ApiError::Internal => "internal server error".to_string(),
+            ApiError::BadGateway => "upstream request failed".to_string(),
+            ApiError::GatewayTimeout => "upstream did not respond in time".to_string(),
+            ApiError::NoHealthyUpstream => "no healthy upstream available".to_string(),
+        }
+    }
+
+    /// Maps a failure while reading a request body, which is a 413 when the
+    /// body limit was hit.
+    pub fn from_body_error(err: axum::Error) -> Self {
+        let mut source: Option<&(dyn std::error::Error + 'static)> = err.source();
+        while let Some(cause) = source {
+            if cause.is::<LengthLimitError>() {
+                return ApiError::PayloadTooLarge;
+            }
+            source = cause.source();
+        }
+        ApiError::BadRequest("failed to read request body")
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = Json(json!({
+            "error": self.message(),
+            "code": self.code(),
+            "request_id": request_id::current(),
+        }));
+        match self {
+            ApiError::RateLimited { retry_after } => (
+                self.status(),
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                body,
+            )
+                .into_response(),
+            ApiError::Unauthorized => {
+                (self.status(), [(header::WWW_AUTHENTICATE, "Bearer")], body).into_response()
+            }
+            _ => (self.status(), body).into_response(),
+        }
+    }
+}
+
+pub async fn not_found() -> ApiError {
+    ApiError::NotFound
+}
+
+/// Gives the bare 405 from the router (keeping `Allow`) and the bare 413 from
+/// the body limit layer an `ApiError` body. Proxied responses are the
+/// upstream's own and pass through.
+pub async fn normalize(res: Response) -> Response {
+    if res.extensions().get::<Proxied>().is_some() {
+        return res;
+    }
+    let is_json = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes().starts_with(b"application/json"));
+    if is_json {
+        return res;
+    }
+    match res.status() {
+        StatusCode::METHOD_NOT_ALLOWED => {
+            let allow = res.headers().get(header::ALLOW).cloned();
+            let mut res = ApiError::MethodNotAllowed.into_response();
+            if let Some(allow) = allow {
+                res.headers_mut().insert(header::ALLOW, allow);
+            }
+            res
+        }
+        StatusCode::PAYLOAD_TOO_LARGE => ApiError::PayloadTooLarge.into_response(),
+        _ => res,
+    }
+}
@@ -0,0 +1,29 @@
+use axum::http::{header, HeaderValue};
+use axum::Router;
+use tower_http::set_header::SetResponseHeaderLayer;
+
+use crate::config::SecurityHeadersConfig;
+
+/// Applies the configured security headers to every response, skipping
+/// empty ones. HSTS is off by default; set it when serving over TLS.
+/// Values are checked by `Config`.
+pub fn apply<S: Clone + Send + Sync + 'static>(
+    mut router: Router<S>,
+    config: &SecurityHeadersConfig,
+) -> Router<S> {
+    let headers = [
+        (header::STRICT_TRANSPORT_SECURITY, &config.hsts),
+        (header::X_CONTENT_TYPE_OPTIONS, &config.content_type_options),
+        (header::REFERRER_POLICY, &config.referrer_policy),
+        (header::X_FRAME_OPTIONS, &config.frame_options),
+        (header::CONTENT_SECURITY_POLICY, &config.csp),
+    ];
+    for (name, value) in headers {
+        if value.is_empty() {
+            continue;
+        }
+        let value = HeaderValue::from_str(value).expect("header value checked by Config::validate");
+        router = router.layer(SetResponseHeaderLayer::if_not_present(name, value));
+    }
+    router
+}
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::Json;
+use serde_json::{json, Map, Value};
+
+use crate::{request_id, AppState};
+
+/// Paths the liveness and readiness handlers are mounted on. Middleware that
+/// can reject requests (rate limiting) lets these through, so probes never
+/// restart or drain a healthy instance.
+pub const PROBE_PATHS: &[&str] = &["/healthz", "/readyz", "/health", "/ready"];
+
+/// A named check consulted by `/healthz` or `/readyz`.
+pub trait HealthCheck: Send + Sync {
+    fn name(&self) -> &str;
+    fn check(&self) -> Result<(), String>;
+}
+
+/// Liveness checks decide whether the process should be restarted; readiness
+/// checks (which include the liveness ones) decide whether it gets traffic.
+pub struct Health {
+    liveness: Vec<Arc<dyn HealthCheck>>,
+    readiness: Vec<Arc<dyn HealthCheck>>,
+}
+
+impl Health {
+    pub fn new(liveness: Vec<Arc<dyn HealthCheck>>, readiness: Vec<Arc<dyn HealthCheck>>) -> Self {
+        Self { liveness, readiness }
+    }
+}
+
+pub async fn liveness(State(state): State<AppState>) -> (StatusCode, Json<Value>) {
+    report(state.health.liveness.iter())
+}
+
+pub async fn readiness(State(state): State<AppState>) -> (StatusCode, Json<Value>) {
+    report(state.health.liveness.iter().chain(&state.health.readiness))
+}
+
+fn report<'a>(checks: impl Iterator<Item = &'a Arc<dyn HealthCheck>>) -> (StatusCode, Json<Value>) {
+    let mut healthy = true;
+    let mut results = Map::new();
+    for check in checks {
+        let result = match check.check() {
+            Ok(()) => json!({"status": "ok"}),
+            Err(error) => {
+                healthy = false;
+                json!({"status": "failing", "error": error})
+            }
+        };
+        results.insert(check.name().to_string(), result);
+    }
+
+    let (status, label) = if healthy {
+        (StatusCode::OK, "ok")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "failing")
+    };
+    (
+        status,
+        Json(json!({
+            "status": label,
+            "checks": results,
+            "request_id": request_id::current(),
+        })),
+    )
+}
@@ -0,0 +1,84 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::time::Duration;
+
+use axum_server::accept::Accept;
+use axum_server::Server;
+use hyper_util::rt::TokioTimer;
+use tokio::net::TcpStream;
+
+use crate::config::ServerConfig;
+
+/// Applies the `[server]` connection settings to either listener.
+pub fn configure<A>(mut server: Server<A>, config: &ServerConfig) -> Server<A> {
+    let builder = server.http_builder();
+    builder
+        .http1()
+        .timer(TokioTimer::new())
+        .keep_alive(config.keep_alive)
+        .header_read_timeout(Duration::from_secs(config.header_read_timeout_secs));
+    builder
+        .http2()
+        .timer(TokioTimer::new())
+        .max_concurrent_streams(config.http2_max_concurrent_streams)
+        .keep_alive_interval(config.http2_keep_alive_interval_secs.map(Duration::from_secs))
+        .keep_alive_timeout(Duration::from_secs(config.http2_keep_alive_timeout_secs));
+
+    server
+}
+
+/// Leaf acceptor that sets `TCP_NODELAY` on each accepted socket and, with
+/// HTTP/2 disabled, drops connections opening with the h2c preface (hyper
+/// would otherwise auto-detect it). Wrap it in the TLS acceptor for HTTPS.
+#[derive(Clone, Copy)]
+pub struct TcpAcceptor {
+    nodelay: bool,
+    reject_h2c: bool,
+    preface_timeout: Duration,
+}
+
+impl TcpAcceptor {
+    pub fn new(config: &ServerConfig) -> Self {
+        Self {
+            nodelay: config.tcp_nodelay,
+            reject_h2c: !config.http2,
+            preface_timeout: Duration::from_secs(config.header_read_timeout_secs),
+        }
+    }
+
+    async fn is_h2c(&self, stream: &TcpStream) -> bool {
+        let mut buf = [0; H2C_PREFACE.len()];
+        let peek = async {
+            loop {
+                let n = stream.peek(&mut buf).await?;
+                if n == 0 || n == buf.len() || !H2C_PREFACE.starts_with(&buf[..n]) {
+                    return io::Result::Ok(&buf[..n] == H2C_PREFACE);
+                }
+                // Partial preface: peek returns at once, so wait for more bytes.
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        matches!(tokio::time::timeout(self.preface_timeout, peek).await, Ok(Ok(true)))
+    }
+}
+
+/// Start of the HTTP/2 connection preface; no HTTP/1 method is `PRI`.
+const H2C_PREFACE: &[u8] = b"PRI * HTTP/2.0";
+
+impl<S: Send + 'static> Accept<TcpStream, S> for TcpAcceptor {
+    type Stream = TcpStream;
+    type Service = S;
+    type Future = Pin<Box<dyn Future<Output = io::Result<(TcpStream, S)>> + Send>>;
+
+    fn accept(&self, stream: TcpStream, service: S) -> Self::Future {
+        let acceptor = *self;
+        Box::pin(async move {
+            stream.set_nodelay(acceptor.nodelay)?;
+            if acceptor.reject_h2c && acceptor.is_h2c(&stream).await {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "HTTP/2 is disabled"));
+            }
+            Ok((stream, service))
+        })
+    }
+}
@@ -0,0 +1,55 @@
+use std::io::IsTerminal;
+use std::time::Duration;
+
+use axum::extract::Request;
+use axum::response::Response;
+use axum::Router;
+use tower_http::trace::TraceLayer;
+use tracing::Span;
+use tracing_subscriber::EnvFilter;
+
+use crate::{request_id, trace_context};
+
+/// Installs the global subscriber. `json` switches from pretty output to one
+/// JSON object per line; `RUST_LOG` sets the filter.
+pub fn init(format: &str) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_ansi(std::io::stdout().is_terminal());
+
+    match format {
+        "json" => builder.json().init(),
+        "pretty" => builder.init(),
+        other => panic!("Invalid log format: {}", other),
+    }
+}
+
+pub fn apply<S: Clone + Send + Sync + 'static>(router: Router<S>, instance_id: &str) -> Router<S> {
+    let instance_id = instance_id.to_string();
+    router.layer(
+        TraceLayer::new_for_http()
+            .make_span_with(move |req: &Request| {
+                let (trace_id, span_id, parent_span_id) = trace_context::of(req);
+                tracing::info_span!(
+                    "request",
+                    request_id = %request_id::of(req),
+                    trace_id = %trace_id,
+                    span_id = %span_id,
+                    parent_span_id = %parent_span_id,
+                    method = %req.method(),
+                    path = %req.uri().path(),
+                    instance_id = %instance_id,
+                )
+            })
+            .on_request(())
+            .on_failure(())
+            .on_response(|res: &Response, latency: Duration, _span: &Span| {
+                tracing::info!(
+                    status = res.status().as_u16(),
+                    latency_ms = latency.as_secs_f64() * 1000.0,
+                    "request completed"
+                );
+            }),
+    )
+}
@@ -1,31 +1,147 @@
-use axum::{routing::get, Router, response::Json};
-use serde_json::json;
+mod capture;
+mod config;
+mod cors;
+mod crash;
+mod error;
+mod headers;
+mod health;
+mod listener;
+mod logging;
+mod manifest;
+mod proxy;
+mod ratelimit;
+mod request_id;
+mod shutdown;
+mod tls;
+mod trace_context;
+
+use axum::{routing::{any, get}, Router};
 use std::net::SocketAddr;
-use std::env;
+use std::sync::Arc;
+
+use capture::RecentRequests;
+use config::Config;
+use health::{Health, HealthCheck};
+use manifest::Manifest;
+use listener::TcpAcceptor;
+use proxy::Proxy;
+use ratelimit::RateLimitLayer;
+use shutdown::Shutdown;
+use tower_http::catch_panic::CatchPanicLayer;
+use tower_http::compression::CompressionLayer;
+use tower_http::limit::RequestBodyLimitLayer;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub manifest: Arc<Manifest>,
+    pub health: Arc<Health>,
+    pub recent: RecentRequests,
+    pub proxy: Option<Arc<Proxy>>,
+}
 
 #[tokio::main]
 async fn main() {
-    let port: u16 = env::var("PORT")
-        .unwrap_or_else(|_| "8080".to_string())
-        .parse()
-        .expect("Invalid PORT");
+    let config = Config::load();
+    let instance_id = config.instance_id();
+
+    logging::init(&config.log_format);
+    crash::install_hook(config.crash_report_dir.clone());
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
+    let shutdown = Shutdown::new(&config.shutdown);
+    let mut readiness: Vec<Arc<dyn HealthCheck>> = vec![Arc::new(shutdown.clone())];
 
-    let app = Router::new()
+    let proxy = (!config.proxy.upstreams.is_empty()).then(|| Arc::new(Proxy::new(&config.proxy)));
+    if let Some(proxy) = &proxy {
+        proxy.spawn_health_checks();
+        readiness.push(proxy.clone());
+        tracing::info!(upstreams = ?proxy.upstreams(), "Proxying /api/* to upstreams");
+    }
+
+    let state = AppState {
+        manifest: Arc::new(Manifest::new(&config)),
+        health: Arc::new(Health::new(vec![], readiness)),
+        recent: RecentRequests::new(&config.request_capture),
+        proxy: proxy.clone(),
+    };
+
+    let mut router = Router::new()
         .route("/", get(root))
-        .route("/health", get(health));
+        .route("/healthz", get(health::liveness))
+        .route("/readyz", get(health::readiness))
+        .route("/health", get(health::liveness))
+        .route("/ready", get(health::readiness))
+        .route("/api/manifest", get(manifest::manifest));
+    if state.recent.enabled() {
+        let authorize = axum::middleware::from_fn_with_state(state.recent.clone(), capture::authorize);
+        router = router.route(
+            "/admin/requests",
+            get(capture::list).delete(capture::clear).route_layer(authorize),
+        );
+    }
+    if let Some(proxy) = &proxy {
+        router = router.route("/api/*path", any(proxy::forward).with_state(proxy.clone()));
+    }
+    let mut app = router
+        .fallback(error::not_found)
+        .layer(axum::middleware::from_fn_with_state(state.clone(), capture::capture))
+        .with_state(state)
+        .layer(RequestBodyLimitLayer::new(config.max_body_bytes))
+        .layer(axum::middleware::map_response(error::normalize))
+        .layer(CatchPanicLayer::custom(crash::panic_response))
+        .layer(axum::middleware::from_fn(crash::track_request));
+    if let Some(limit) = &config.rate_limit {
+        let burst = limit.burst.unwrap_or_else(|| limit.rps.max(1.0));
+        app = app.layer(RateLimitLayer::new(limit.rps, burst));
+    }
+    let mut app = headers::apply(app, &config.security_headers);
+    if let Some(cors) = cors::layer(&config.cors) {
+        app = app.layer(cors);
+    }
+    if config.compression {
+        app = app.layer(CompressionLayer::new());
+    }
+    let app = logging::apply(app, &instance_id);
+    let app = trace_context::apply(app);
+    let app = request_id::apply(app);
 
-    println!("Server running on http://{}", addr);
+    let handle = axum_server::Handle::new();
+    tokio::spawn({
+        let handle = handle.clone();
+        let signal = shutdown.clone().signal();
+        async move {
+            signal.await;
+            handle.graceful_shutdown(None);
+        }
+    });
 
-    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    let service = app.into_make_service_with_connect_info::<SocketAddr>();
+    let tls = match &config.tls {
+        Some(tls) => Some(tls::load(tls, config.server.http2).await),
+        None => None,
+    };
+    let acceptor = TcpAcceptor::new(&config.server);
+    let server = async {
+        match tls {
+            Some(tls) => {
+                tracing::info!(%addr, %instance_id, "Server running with TLS");
+                let server = axum_server::bind_rustls(addr, tls).map(|tls| tls.acceptor(acceptor));
+                listener::configure(server, &config.server).handle(handle).serve(service).await
+            }
+            None => {
+                tracing::info!(%addr, %instance_id, "Server running");
+                let server = axum_server::bind(addr).acceptor(acceptor);
+                listener::configure(server, &config.server).handle(handle).serve(service).await
+            }
+        }
+    };
+
+    tokio::select! {
+        result = server => result.unwrap(),
+        _ = shutdown.drain_deadline() => tracing::warn!("Dropping remaining connections"),
+    }
 }
 
 async fn root() -> &'static str {
     "dumb-server"
 }
-
-async fn health() -> Json<serde_json::Value> {
-    Json(json!({"status": "ok"}))
-}
\ No newline at end of file
@@ -0,0 +1,54 @@
+use axum::{extract::State, response::Json};
+use serde_json::{json, Value};
+
+use crate::config::Config;
+use crate::{request_id, AppState};
+
+/// The parts of the manifest fixed by configuration, resolved at startup.
+/// New optional features should add themselves to `subsystems`.
+pub struct Manifest {
+    instance_id: String,
+    subsystems: Vec<&'static str>,
+    ports: Value,
+}
+
+impl Manifest {
+    pub fn new(config: &Config) -> Self {
+        let enabled = [
+            ("health", true),
+            ("readiness", true),
+            ("request_capture", config.request_capture.size > 0),
+            ("rate_limit", config.rate_limit.is_some()),
+            ("tls", config.tls.is_some()),
+            ("http2", config.server.http2),
+            ("cors", !config.cors.allowed_origins.is_empty()),
+            ("compression", config.compression),
+            ("proxy", !config.proxy.upstreams.is_empty()),
+        ];
+        let scheme = if config.tls.is_some() { "https" } else { "http" };
+
+        Self {
+            instance_id: config.instance_id(),
+            subsystems: enabled.iter().filter(|(_, on)| *on).map(|(name, _)| *name).collect(),
+            ports: json!([{"name": scheme, "port": config.port}]),
+        }
+    }
+}
+
+/// Machine-readable description of this instance for fleet inventory.
+pub async fn manifest(State(state): State<AppState>) -> Json<Value> {
+    let manifest = &state.manifest;
+    Json(json!({
+        "name": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "instance_id": manifest.instance_id,
+        "role": if state.proxy.is_some() { "gateway" } else { "service" },
+        "subsystems": manifest.subsystems,
+        "ports": manifest.ports,
+        "storage": null,
+        "peers": [],
+        "upstreams": state.proxy.as_ref().map(|p| p.upstreams()).unwrap_or_default(),
+        "functions": [],
+        "request_id": request_id::current(),
+    }))
+}
@@ -0,0 +1,347 @@
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::uri::{PathAndQuery, Uri};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Version};
+use axum::response::{IntoResponse, Response};
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use tokio::task::JoinSet;
+use tokio::time::MissedTickBehavior;
+
+use crate::config::ProxyConfig;
+use crate::error::ApiError;
+use crate::health::HealthCheck;
+
+/// Headers that describe a single hop and must not be forwarded.
+const HOP_BY_HOP: &[HeaderName] = &[
+    header::CONNECTION,
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+/// Response extension marking a response relayed from an upstream, which
+/// `error::normalize` must pass through untouched.
+#[derive(Clone, Copy)]
+pub struct Proxied;
+
+struct Upstream {
+    base: Uri,
+    healthy: AtomicBool,
+}
+
+/// Round-robin reverse proxy over plain-HTTP upstreams. Upstreams failing
+/// their health check, or a forwarded request, are skipped until the next
+/// successful check.
+pub struct Proxy {
+    upstreams: Vec<Upstream>,
+    next: AtomicUsize,
+    client: Client<HttpConnector, Body>,
+    timeout: Duration,
+    health_path: String,
+    health_interval: Duration,
+}
+
+impl Proxy {
+    /// Expects http:// upstreams with an authority, as checked by `Config`.
+    pub fn new(config: &ProxyConfig) -> Self {
+        let upstreams = config
+            .upstreams
+            .iter()
+            .map(|u| Upstream {
+                base: u.parse().expect("upstream checked by Config::validate"),
+                healthy: AtomicBool::new(true),
+            })
+            .collect();
+
+        Self {
+            upstreams,
+            next: AtomicUsize::new(0),
+            client: Client::builder(TokioExecutor::new()).build_http(),
+            timeout: Duration::from_secs(config.timeout_secs),
+            health_path: config.health_path.clone(),
+            health_interval: Duration::from_secs(config.health_interval_secs),
+        }
+    }
+
+    pub fn upstreams(&self) -> Vec<String> {
+        self.upstreams.iter().map(|u| u.base.to_string()).collect()
+    }
+
+    /// Probes every upstream on `health_interval` and updates its state.
+    /// Probes run concurrently, so a hanging upstream doesn't delay the rest.
+    pub fn spawn_health_checks(self: &Arc<Self>) {
+        let proxy = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(proxy.health_interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let mut probes = JoinSet::new();
+                for i in 0..proxy.upstreams.len() {
+                    let proxy = proxy.clone();
+                    probes.spawn(async move {
+                        let upstream = &proxy.upstreams[i];
+                        proxy.mark(upstream, proxy.probe(upstream).await);
+                    });
+                }
+                while probes.join_next().await.is_some() {}
+            }
+        });
+    }
+
+    /// A probe gets at most one health interval, however long `timeout` is.
+    async fn probe(&self, upstream: &Upstream) -> bool {
+        let uri = join(&upstream.base, &self.health_path);
+        let req = Request::get(uri).body(Body::empty()).expect("valid health request");
+        let timeout = self.timeout.min(self.health_interval);
+        match tokio::time::timeout(timeout, self.client.request(req)).await {
+            Ok(Ok(res)) => res.status().is_success(),
+            _ => false,
+        }
+    }
+
+    fn mark(&self, upstream: &Upstream, healthy: bool) {
+        if upstream.healthy.swap(healthy, Ordering::Relaxed) != healthy {
+            if healthy {
+                tracing::info!(upstream = %upstream.base, "Upstream back in rotation");
+            } else {
+                tracing::warn!(upstream = %upstream.base, "Upstream ejected");
+            }
+        }
+    }
+
+    fn pick(&self) -> Option<&Upstream> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        (0..self.upstreams.len())
+            .map(|i| &self.upstreams[(start + i) % self.upstreams.len()])
+            .find(|u| u.healthy.load(Ordering::Relaxed))
+    }
+}
+
+impl HealthCheck for Proxy {
+    fn name(&self) -> &str {
+        "upstreams"
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if self.upstreams.iter().any(|u| u.healthy.load(Ordering::Relaxed)) {
+            Ok(())
+        } else {
+            Err("no healthy upstreams".to_string())
+        }
+    }
+}
+
+pub async fn forward(State(proxy): State<Arc<Proxy>>, req: Request) -> Response {
+    let Some(upstream) = proxy.pick() else {
+        return ApiError::NoHealthyUpstream.into_response();
+    };
+
+    let client_ip = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string());
+    let (mut parts, body) = req.into_parts();
+    let path = parts.uri.path_and_query().map(PathAndQuery::as_str).unwrap_or("/");
+    parts.uri = join(&upstream.base, path);
+    // The client only speaks HTTP/1.1 upstream, whatever the caller used.
+    parts.version = Version::HTTP_11;
+    strip_hop_by_hop(&mut parts.headers);
+    parts.headers.remove(header::HOST);
+    if let Some(ip) = client_ip {
+        let forwarded = match parts.headers.get("x-forwarded-for").and_then(|v| v.to_str().ok()) {
+            Some(prior) => format!("{}, {}", prior, ip),
+            None => ip,
+        };
+        if let Ok(value) = HeaderValue::from_str(&forwarded) {
+            parts.headers.insert("x-forwarded-for", value);
+        }
+    }
+
+    let req = Request::from_parts(parts, body);
+    match tokio::time::timeout(proxy.timeout, proxy.client.request(req)).await {
+        Ok(Ok(res)) => {
+            let (mut parts, body) = res.into_parts();
+            strip_hop_by_hop(&mut parts.headers);
+            parts.extensions.insert(Proxied);
+            Response::from_parts(parts, Body::new(body))
+        }
+        Ok(Err(err)) => {
+            tracing::warn!(upstream = %upstream.base, %err, "Upstream request failed");
+            if err.is_connect() {
+                proxy.mark(upstream, false);
+            }
+            ApiError::BadGateway.into_response()
+        }
+        Err(_) => ApiError::GatewayTimeout.into_response(),
+    }
+}
+
+fn join(base: &Uri, path_and_query: &str) -> Uri {
+    let prefix = base.path().trim_end_matches('/');
+    Uri::builder()
+        .scheme(base.scheme_str().unwrap_or("http"))
+        .authority(base.authority().expect("upstream authority").as_str())
+        .path_and_query(format!("{}{}", prefix, path_and_query))
+        .build()
+        .expect("valid upstream URI")
+}
+
+fn strip_hop_by_hop(headers: &mut HeaderMap) {
+    for name in HOP_BY_HOP {
+        headers.remove(name);
+    }
+    headers.remove("keep-alive");
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use axum::routing::{any, get};
+    use axum::Router;
+    use http_body_util::BodyExt;
+
+    use super::*;
+
+    fn serve(router: Router) -> SocketAddr {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(axum_server::from_tcp(listener).serve(router.into_make_service()));
+        addr
+    }
+
+    fn proxy(upstreams: &[String]) -> Arc<Proxy> {
+        Arc::new(Proxy::new(&ProxyConfig {
+            upstreams: upstreams.to_vec(),
+            health_interval_secs: 1,
+            ..ProxyConfig::default()
+        }))
+    }
+
+    fn healthy(proxy: &Proxy) -> Vec<bool> {
+        proxy.upstreams.iter().map(|u| u.healthy.load(Ordering::Relaxed)).collect()
+    }
+
+    /// Port 1 on loopback is closed, so connecting fails fast.
+    const DEAD: &str = "http://127.0.0.1:1";
+
+    #[test]
+    fn picks_healthy_upstreams_round_robin() {
+        let proxy = proxy(&["http://a:1".into(), "http://b:1".into(), "http://c:1".into()]);
+        let picks = |n| -> Vec<String> {
+            (0..n).map(|_| proxy.pick().unwrap().base.to_string()).collect()
+        };
+        assert_eq!(picks(4), ["http://a:1/", "http://b:1/", "http://c:1/", "http://a:1/"]);
+
+        proxy.mark(&proxy.upstreams[1], false);
+        assert!(!picks(6).contains(&"http://b:1/".to_string()));
+        assert!(proxy.check().is_ok());
+
+        proxy.mark(&proxy.upstreams[0], false);
+        proxy.mark(&proxy.upstreams[2], false);
+        assert!(proxy.pick().is_none());
+        assert_eq!(proxy.check(), Err("no healthy upstreams".to_string()));
+    }
+
+    #[tokio::test]
+    async fn ejects_an_upstream_on_connect_error() {
+        let proxy = proxy(&[DEAD.into()]);
+        let req = Request::get("/api/x").body(Body::empty()).unwrap();
+
+        let res = forward(State(proxy.clone()), req).await;
+        assert_eq!(res.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(healthy(&proxy), [false]);
+
+        let req = Request::get("/api/x").body(Body::empty()).unwrap();
+        let res = forward(State(proxy.clone()), req).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn readmits_an_upstream_after_a_passing_probe() {
+        let upstream = serve(Router::new().route("/health", get(|| async { "ok" })));
+        let proxy = proxy(&[format!("http://{}", upstream)]);
+        proxy.mark(&proxy.upstreams[0], false);
+        assert!(proxy.check().is_err());
+
+        let healthy_now = proxy.probe(&proxy.upstreams[0]).await;
+        proxy.mark(&proxy.upstreams[0], healthy_now);
+        assert_eq!(healthy(&proxy), [true]);
+        assert!(proxy.check().is_ok());
+    }
+
+    #[tokio::test]
+    async fn probes_run_concurrently() {
+        // Accepts connections into the backlog but never answers.
+        let hanging = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy = proxy(&[format!("http://{}", hanging.local_addr().unwrap()), DEAD.into()]);
+        proxy.spawn_health_checks();
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(healthy(&proxy), [true, false], "dead upstream ejected while the other hangs");
+    }
+
+    #[tokio::test]
+    async fn forwards_http2_requests_upstream_as_http1() {
+        let upstream = serve(Router::new().route(
+            "/api/version",
+            get(|req: Request| async move { format!("{:?}", req.version()) }),
+        ));
+        let proxy = Arc::new(Proxy::new(&ProxyConfig {
+            upstreams: vec![format!("http://{}", upstream)],
+            ..ProxyConfig::default()
+        }));
+        let gateway = serve(Router::new().route("/api/*path", any(forward)).with_state(proxy));
+
+        let client = Client::builder(TokioExecutor::new())
+            .http2_only(true)
+            .build_http::<Body>();
+        let req = Request::get(format!("http://{}/api/version", gateway))
+            .body(Body::empty())
+            .unwrap();
+        let res = client.request(req).await.unwrap();
+
+        assert_eq!(res.version(), Version::HTTP_2);
+        assert_eq!(res.status(), 200);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "HTTP/1.1");
+    }
+
+    #[tokio::test]
+    async fn relays_upstream_errors_without_normalizing_them() {
+        let upstream = serve(Router::new().route(
+            "/api/quota",
+            get(|| async { (StatusCode::PAYLOAD_TOO_LARGE, "upstream quota exceeded") }),
+        ));
+        let proxy = Arc::new(Proxy::new(&ProxyConfig {
+            upstreams: vec![format!("http://{}", upstream)],
+            ..ProxyConfig::default()
+        }));
+        let gateway = serve(
+            Router::new()
+                .route("/api/*path", any(forward))
+                .with_state(proxy)
+                .layer(axum::middleware::map_response(crate::error::normalize)),
+        );
+
+        let client = Client::builder(TokioExecutor::new()).build_http::<Body>();
+        let req = Request::get(format!("http://{}/api/quota", gateway))
+            .body(Body::empty())
+            .unwrap();
+        let res = client.request(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "upstream quota exceeded");
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use axum::extract::{ConnectInfo, Request};
+use axum::response::{IntoResponse, Response};
+use tower::{Layer, Service};
+
+use crate::error::ApiError;
+use crate::health;
+
+/// Past this many tracked clients, idle (fully refilled) buckets are dropped,
+/// at most once per `PRUNE_INTERVAL` so the sweep is not paid per request.
+const PRUNE_THRESHOLD: usize = 10_000;
+const PRUNE_INTERVAL: Duration = Duration::from_secs(10);
+/// Hard cap on tracked clients. Once reached, new clients share one overflow
+/// bucket until pruning frees room, so memory stays bounded under churn.
+const MAX_BUCKETS: usize = 100_000;
+const OVERFLOW_KEY: &str = "overflow";
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+struct Buckets {
+    map: HashMap<String, Bucket>,
+    pruned: Instant,
+}
+
+struct Limiter {
+    rps: f64,
+    burst: f64,
+    prune_threshold: usize,
+    max_buckets: usize,
+    buckets: Mutex<Buckets>,
+}
+
+impl Limiter {
+    fn new(rps: f64, burst: f64) -> Self {
+        Self {
+            rps,
+            burst,
+            prune_threshold: PRUNE_THRESHOLD,
+            max_buckets: MAX_BUCKETS,
+            buckets: Mutex::new(Buckets {
+                map: HashMap::new(),
+                pruned: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes a token for `key`, or returns how long until one is available.
+    fn acquire(&self, key: String) -> Result<(), Duration> {
+        self.acquire_at(key, Instant::now())
+    }
+
+    fn acquire_at(&self, mut key: String, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+
+        let sweep_due = now.duration_since(buckets.pruned) >= PRUNE_INTERVAL;
+        if buckets.map.len() >= self.prune_threshold && sweep_due {
+            let (rps, burst) = (self.rps, self.burst);
+            buckets
+                .map
+                .retain(|_, b| b.tokens + now.duration_since(b.updated).as_secs_f64() * rps < burst);
+            buckets.pruned = now;
+        }
+        if buckets.map.len() >= self.max_buckets && !buckets.map.contains_key(&key) {
+            key = OVERFLOW_KEY.to_string();
+        }
+
+        let bucket = buckets.map.entry(key).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rps).min(self.burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rps))
+        }
+    }
+}
+
+/// Token-bucket rate limiting keyed by client IP; `X-Api-Key` is not
+/// authenticated, so it is not trusted as a key. Probe routes are exempt.
+/// Requires the router to be served with `ConnectInfo<SocketAddr>`.
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    limiter: Arc<Limiter>,
+}
+
+impl RateLimitLayer {
+    /// Expects `rps > 0` and `burst >= 1`, as checked by `Config`.
+    pub fn new(rps: f64, burst: f64) -> Self {
+        Self {
+            limiter: Arc::new(Limiter::new(rps, burst)),
+        }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    limiter: Arc<Limiter>,
+}
+
+impl<S> Service<Request> for RateLimit<S>
+where
+    S: Service<Request, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        if health::PROBE_PATHS.contains(&req.uri().path()) {
+            return Box::pin(self.inner.call(req));
+        }
+        match self.limiter.acquire(client_key(&req)) {
+            Ok(()) => Box::pin(self.inner.call(req)),
+            Err(wait) => Box::pin(async move { Ok(too_many_requests(wait)) }),
+        }
+    }
+}
+
+/// IPv6 clients are keyed by their /64, which a single host can rotate
+/// addresses within freely.
+fn client_key(req: &Request) -> String {
+    match req.extensions().get::<ConnectInfo<SocketAddr>>() {
+        Some(ConnectInfo(addr)) => match addr.ip() {
+            IpAddr::V4(ip) => ip.to_string(),
+            IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+                Some(ip) => ip.to_string(),
+                None => {
+                    let prefix = Ipv6Addr::from(u128::from(ip) & u128::MAX << 64);
+                    format!("{}/64", prefix)
+                }
+            },
+        },
+        None => "unknown".to_string(),
+    }
+}
+
+fn too_many_requests(wait: Duration) -> Response {
+    let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+    ApiError::RateLimited { retry_after }.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(rps: f64, burst: f64) -> Limiter {
+        Limiter::new(rps, burst)
+    }
+
+    fn small_limiter(prune_threshold: usize, max_buckets: usize) -> Limiter {
+        Limiter {
+            prune_threshold,
+            max_buckets,
+            ..Limiter::new(1.0, 2.0)
+        }
+    }
+
+    #[test]
+    fn allows_a_full_burst_then_rejects() {
+        let limiter = limiter(1.0, 3.0);
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.acquire_at("a".into(), now).is_ok());
+        }
+        assert_eq!(limiter.acquire_at("a".into(), now), Err(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn retry_after_covers_the_missing_fraction_of_a_token() {
+        let limiter = limiter(4.0, 1.0);
+        let now = Instant::now();
+        assert!(limiter.acquire_at("a".into(), now).is_ok());
+
+        let wait = limiter.acquire_at("a".into(), now + Duration::from_millis(100)).unwrap_err();
+        assert!((wait.as_secs_f64() - 0.15).abs() < 1e-9, "{:?}", wait);
+    }
+
+    #[test]
+    fn refills_at_rps_and_caps_at_burst() {
+        let limiter = limiter(2.0, 2.0);
+        let now = Instant::now();
+        assert!(limiter.acquire_at("a".into(), now).is_ok());
+        assert!(limiter.acquire_at("a".into(), now).is_ok());
+        assert!(limiter.acquire_at("a".into(), now).is_err());
+
+        // Half a second at 2 rps refills exactly one token.
+        let later = now + Duration::from_millis(500);
+        assert!(limiter.acquire_at("a".into(), later).is_ok());
+        assert!(limiter.acquire_at("a".into(), later).is_err());
+
+        // A long idle period refills to `burst`, not beyond.
+        let much_later = later + Duration::from_secs(60);
+        assert!(limiter.acquire_at("a".into(), much_later).is_ok());
+        assert!(limiter.acquire_at("a".into(), much_later).is_ok());
+        assert!(limiter.acquire_at("a".into(), much_later).is_err());
+    }
+
+    #[test]
+    fn buckets_are_independent_per_key() {
+        let limiter = limiter(1.0, 1.0);
+        let now = Instant::now();
+        assert!(limiter.acquire_at("a".into(), now).is_ok());
+        assert!(limiter.acquire_at("a".into(), now).is_err());
+        assert!(limiter.acquire_at("b".into(), now).is_ok());
+    }
+
+    #[test]
+    fn prunes_only_refilled_buckets_past_the_threshold() {
+        let limiter = small_limiter(3, 100);
+        let start = Instant::now() + PRUNE_INTERVAL;
+        for key in ["busy", "busy", "idle1", "idle2"] {
+            limiter.acquire_at(key.into(), start).unwrap();
+        }
+
+        // 1.5s later the idle buckets (one token spent) are full again;
+        // "busy" (both spent) is not.
+        limiter.acquire_at("new".into(), start + Duration::from_millis(1500)).unwrap();
+        let buckets = limiter.buckets.lock().unwrap();
+        let mut keys: Vec<_> = buckets.map.keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, ["busy", "new"]);
+    }
+
+    #[test]
+    fn prunes_at_most_once_per_interval() {
+        let limiter = small_limiter(2, 100);
+        let start = Instant::now() + PRUNE_INTERVAL;
+        limiter.acquire_at("a".into(), start).unwrap();
+        limiter.acquire_at("b".into(), start).unwrap();
+        limiter.acquire_at("c".into(), start + Duration::from_secs(2)).unwrap();
+        assert_eq!(limiter.buckets.lock().unwrap().map.len(), 1, "first sweep runs");
+
+        limiter.acquire_at("d".into(), start + Duration::from_secs(4)).unwrap();
+        limiter.acquire_at("e".into(), start + Duration::from_secs(6)).unwrap();
+        assert_eq!(limiter.buckets.lock().unwrap().map.len(), 3, "no sweep within the interval");
+
+        let later = start + Duration::from_secs(2) + PRUNE_INTERVAL;
+        limiter.acquire_at("f".into(), later).unwrap();
+        assert_eq!(limiter.buckets.lock().unwrap().map.len(), 1, "next sweep after the interval");
+    }
+
+    #[test]
+    fn new_clients_share_an_overflow_bucket_at_the_cap() {
+        let limiter = small_limiter(usize::MAX, 2);
+        let now = Instant::now();
+        limiter.acquire_at("a".into(), now).unwrap();
+        limiter.acquire_at("b".into(), now).unwrap();
+
+        limiter.acquire_at("c".into(), now).unwrap();
+        limiter.acquire_at("d".into(), now).unwrap();
+        assert!(limiter.acquire_at("e".into(), now).is_err(), "c, d and e share one bucket");
+        assert!(limiter.acquire_at("a".into(), now).is_ok(), "tracked clients keep theirs");
+
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.map.len(), 3);
+        assert!(buckets.map.contains_key(OVERFLOW_KEY));
+    }
+
+    fn key_for(addr: &str) -> String {
+        let mut req = Request::new(axum::body::Body::empty());
+        req.extensions_mut().insert(ConnectInfo(addr.parse::<SocketAddr>().unwrap()));
+        client_key(&req)
+    }
+
+    #[test]
+    fn keys_ipv6_clients_by_their_64_prefix() {
+        assert_eq!(key_for("192.0.2.7:1234"), "192.0.2.7");
+        assert_eq!(key_for("[2001:db8:1:2:aaaa::1]:1"), "2001:db8:1:2::/64");
+        assert_eq!(key_for("[2001:db8:1:2:bbbb::9]:1"), "2001:db8:1:2::/64");
+        assert_eq!(key_for("[::ffff:192.0.2.7]:1"), "192.0.2.7");
+    }
+
+    #[test]
+    fn retry_after_header_rounds_up_to_whole_seconds() {
+        let res = too_many_requests(Duration::from_millis(150));
+        assert_eq!(res.status(), 429);
+        assert_eq!(res.headers()["retry-after"], "1");
+
+        let res = too_many_requests(Duration::from_millis(2100));
+        assert_eq!(res.headers()["retry-after"], "3");
+    }
+}
@@ -0,0 +1,37 @@
+use axum::extract::Request;
+use axum::middleware::{self, Next};
+use axum::response::Response;
+use axum::Router;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Honors an incoming `X-Request-Id` or generates a UUID, echoes it on the
+/// response and makes it available to everything inside via [`current`].
+/// Apply last so it wraps the trace layer.
+pub fn apply<S: Clone + Send + Sync + 'static>(router: Router<S>) -> Router<S> {
+    router
+        .layer(middleware::from_fn(scope))
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+}
+
+/// The ID of the request currently being handled, if any.
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Reads the ID set by `SetRequestIdLayer` from the request headers.
+pub fn of(req: &Request) -> &str {
+    req.headers()
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+}
+
+async fn scope(req: Request, next: Next) -> Response {
+    let id = of(&req).to_string();
+    REQUEST_ID.scope(id, next.run(req)).await
+}
@@ -0,0 +1,114 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::signal::unix::{signal, Signal, SignalKind};
+use tokio::sync::watch;
+
+use crate::config::ShutdownConfig;
+use crate::health::HealthCheck;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Phase {
+    Running,
+    Draining,
+    Forced,
+}
+
+/// SIGTERM choreography: fail readiness, keep serving for `grace`, then drain
+/// in-flight requests for at most `drain`. SIGINT skips the grace window, and
+/// a second signal at any point after the first exits immediately.
+#[derive(Clone)]
+pub struct Shutdown {
+    grace: Duration,
+    drain: Duration,
+    ready: Arc<AtomicBool>,
+    phase: Arc<watch::Sender<Phase>>,
+}
+
+impl Shutdown {
+    /// Installs the SIGTERM/SIGINT handlers right away, so no signal is missed
+    /// between phases. Must be called inside the runtime.
+    pub fn new(config: &ShutdownConfig) -> Self {
+        let shutdown = Self {
+            grace: Duration::from_secs(config.grace_secs),
+            drain: Duration::from_secs(config.drain_secs),
+            ready: Arc::new(AtomicBool::new(true)),
+            phase: Arc::new(watch::Sender::new(Phase::Running)),
+        };
+        let sigterm = signal(SignalKind::terminate()).expect("Failed to install SIGTERM handler");
+        let sigint = signal(SignalKind::interrupt()).expect("Failed to install SIGINT handler");
+        tokio::spawn(shutdown.clone().listen(sigterm, sigint));
+        shutdown
+    }
+
+    /// Resolves once the server should stop accepting connections.
+    pub async fn signal(self) {
+        self.reached(Phase::Draining).await;
+    }
+
+    /// Resolves once draining has started and either the drain window has
+    /// elapsed or another signal arrives.
+    pub async fn drain_deadline(self) {
+        self.reached(Phase::Draining).await;
+        tokio::select! {
+            _ = tokio::time::sleep(self.drain) => tracing::warn!("Drain window elapsed"),
+            _ = self.reached(Phase::Forced) => {}
+        }
+    }
+
+    async fn listen(self, mut sigterm: Signal, mut sigint: Signal) {
+        let graceful = tokio::select! {
+            _ = sigterm.recv() => true,
+            _ = sigint.recv() => false,
+        };
+        self.ready.store(false, Ordering::Relaxed);
+
+        if graceful {
+            tracing::info!(grace = ?self.grace, "SIGTERM received, failing readiness");
+            tokio::select! {
+                _ = tokio::time::sleep(self.grace) => {}
+                _ = next_signal(&mut sigterm, &mut sigint) => {
+                    tracing::warn!("Second signal received, skipping grace and drain");
+                    self.phase.send_replace(Phase::Forced);
+                    return;
+                }
+            }
+        } else {
+            tracing::info!("SIGINT received");
+        }
+
+        tracing::info!(drain = ?self.drain, "Draining in-flight requests");
+        self.phase.send_replace(Phase::Draining);
+
+        next_signal(&mut sigterm, &mut sigint).await;
+        tracing::warn!("Second signal received, skipping drain");
+        self.phase.send_replace(Phase::Forced);
+    }
+
+    async fn reached(&self, phase: Phase) {
+        // The sender lives in `self`, so the channel never closes here.
+        let _ = self.phase.subscribe().wait_for(|current| *current >= phase).await;
+    }
+}
+
+async fn next_signal(sigterm: &mut Signal, sigint: &mut Signal) {
+    tokio::select! {
+        _ = sigterm.recv() => {}
+        _ = sigint.recv() => {}
+    }
+}
+
+impl HealthCheck for Shutdown {
+    fn name(&self) -> &str {
+        "shutdown"
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if self.ready.load(Ordering::Relaxed) {
+            Ok(())
+        } else {
+            Err("shutting down".to_string())
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use std::sync::Arc;
+
+use axum_server::tls_rustls::RustlsConfig;
+use tokio::signal::unix::{signal, SignalKind};
+
+use crate::config::TlsConfig;
+
+/// Loads the certificate pair and reloads it from disk on every SIGHUP.
+/// ALPN offers `h2` only when `http2` is enabled.
+pub async fn load(tls: &TlsConfig, http2: bool) -> RustlsConfig {
+    let (cert, key) = (tls.cert_path.clone(), tls.key_path.clone());
+
+    let config = RustlsConfig::from_pem_file(&cert, &key)
+        .await
+        .unwrap_or_else(|err| panic!("Failed to load TLS certificate: {}", err));
+    set_alpn(&config, http2);
+
+    let reloaded = config.clone();
+    tokio::spawn(async move {
+        let mut sighup = signal(SignalKind::hangup()).expect("Failed to install SIGHUP handler");
+        while sighup.recv().await.is_some() {
+            match reloaded.reload_from_pem_file(&cert, &key).await {
+                Ok(()) => {
+                    set_alpn(&reloaded, http2);
+                    tracing::info!(%cert, "TLS certificate reloaded");
+                }
+                Err(err) => tracing::error!(%cert, %err, "Failed to reload TLS certificate, keeping the old one"),
+            }
+        }
+    });
+
+    config
+}
+
+fn set_alpn(config: &RustlsConfig, http2: bool) {
+    let mut inner = (*config.get_inner()).clone();
+    inner.alpn_protocols = if http2 {
+        vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+    } else {
+        vec![b"http/1.1".to_vec()]
+    };
+    config.reload_from_config(Arc::new(inner));
+}
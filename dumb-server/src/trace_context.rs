@@ -0,0 +1,173 @@
+use axum::extract::Request;
+use axum::http::HeaderValue;
+use axum::middleware::{self, Next};
+use axum::response::Response;
+use axum::Router;
+use uuid::Uuid;
+
+/// Continues an incoming W3C `traceparent` (or starts a new trace) and
+/// rewrites the header with a fresh span ID for this hop, so anything
+/// forwarded upstream carries it. The IDs, including the caller's span as
+/// the parent, are kept in a request extension for the log span. Apply after
+/// the trace layer.
+pub fn apply<S: Clone + Send + Sync + 'static>(router: Router<S>) -> Router<S> {
+    router.layer(middleware::from_fn(propagate))
+}
+
+#[derive(Clone)]
+struct TraceIds {
+    trace_id: String,
+    span_id: String,
+    parent_span_id: String,
+}
+
+/// `(trace_id, span_id, parent_span_id)` for this hop; the parent is empty
+/// when the trace started here.
+pub fn of(req: &Request) -> (&str, &str, &str) {
+    match req.extensions().get::<TraceIds>() {
+        Some(ids) => (&ids.trace_id, &ids.span_id, &ids.parent_span_id),
+        None => ("", "", ""),
+    }
+}
+
+async fn propagate(mut req: Request, next: Next) -> Response {
+    let incoming = req.headers().get("traceparent").and_then(|v| v.to_str().ok());
+    let (trace_id, parent_span_id, flags) = match incoming.and_then(parse) {
+        Some((trace_id, parent_id, flags)) => {
+            (trace_id.to_string(), parent_id.to_string(), flags.to_string())
+        }
+        None => (format!("{:032x}", Uuid::new_v4().as_u128()), String::new(), "01".to_string()),
+    };
+    let span_id = format!("{:016x}", Uuid::new_v4().as_u128() as u64);
+    let traceparent = format!("00-{}-{}-{}", trace_id, span_id, flags);
+    req.headers_mut()
+        .insert("traceparent", HeaderValue::from_str(&traceparent).expect("hex traceparent"));
+    req.extensions_mut().insert(TraceIds {
+        trace_id,
+        span_id,
+        parent_span_id,
+    });
+    next.run(req).await
+}
+
+/// Splits a `traceparent` into trace ID, parent ID and flags, rejecting
+/// malformed or all-zero IDs. Version 00 must have exactly four fields; per
+/// W3C, later versions are read by their first four and may append more.
+fn parse(value: &str) -> Option<(&str, &str, &str)> {
+    let mut parts = value.trim().split('-');
+    let version = parts.next()?;
+    let trace_id = parts.next()?;
+    let parent_id = parts.next()?;
+    let flags = parts.next()?;
+    let hex = |s: &str, len: usize| {
+        s.len() == len && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    };
+    let valid = hex(version, 2)
+        && version != "ff"
+        && (version != "00" || parts.next().is_none())
+        && hex(trace_id, 32)
+        && hex(parent_id, 16)
+        && hex(flags, 2)
+        && trace_id.bytes().any(|b| b != b'0')
+        && parent_id.bytes().any(|b| b != b'0');
+    valid.then_some((trace_id, parent_id, flags))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::routing::get;
+    use http_body_util::BodyExt;
+    use tower::Service;
+
+    use super::*;
+
+    const TRACE: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const PARENT: &str = "00f067aa0ba902b7";
+
+    #[test]
+    fn parses_a_valid_traceparent() {
+        let value = format!("00-{}-{}-01", TRACE, PARENT);
+        assert_eq!(parse(&value), Some((TRACE, PARENT, "01")));
+        assert_eq!(parse(&format!(" {} ", value)), Some((TRACE, PARENT, "01")));
+    }
+
+    #[test]
+    fn rejects_all_zero_ids() {
+        assert_eq!(parse(&format!("00-{}-{}-01", "0".repeat(32), PARENT)), None);
+        assert_eq!(parse(&format!("00-{}-{}-01", TRACE, "0".repeat(16))), None);
+    }
+
+    #[test]
+    fn rejects_uppercase_hex() {
+        let upper = TRACE.to_uppercase();
+        assert_eq!(parse(&format!("00-{}-{}-01", upper, PARENT)), None);
+        assert_eq!(parse(&format!("00-{}-{}-0A", TRACE, PARENT)), None);
+    }
+
+    #[test]
+    fn rejects_wrong_lengths() {
+        assert_eq!(parse(&format!("00-{}-{}-01", &TRACE[1..], PARENT)), None);
+        assert_eq!(parse(&format!("00-{}0-{}-01", TRACE, PARENT)), None);
+        assert_eq!(parse(&format!("00-{}-{}-01", TRACE, &PARENT[1..])), None);
+        assert_eq!(parse(&format!("00-{}-{}-1", TRACE, PARENT)), None);
+    }
+
+    #[test]
+    fn rejects_invalid_versions_and_field_counts() {
+        assert_eq!(parse(&format!("ff-{}-{}-01", TRACE, PARENT)), None);
+        assert_eq!(parse(&format!("0-{}-{}-01", TRACE, PARENT)), None);
+        assert_eq!(parse(&format!("0A-{}-{}-01", TRACE, PARENT)), None);
+        assert_eq!(parse(&format!("00-{}-{}-01-extra", TRACE, PARENT)), None);
+        assert_eq!(parse(&format!("00-{}-{}", TRACE, PARENT)), None);
+        assert_eq!(parse(""), None);
+    }
+
+    #[test]
+    fn reads_the_first_four_fields_of_future_versions() {
+        assert_eq!(parse(&format!("01-{}-{}-01", TRACE, PARENT)), Some((TRACE, PARENT, "01")));
+        assert_eq!(
+            parse(&format!("cc-{}-{}-01-what-the-future-holds", TRACE, PARENT)),
+            Some((TRACE, PARENT, "01"))
+        );
+        assert_eq!(parse(&format!("cc-{}-{}-01x", TRACE, PARENT)), None);
+    }
+
+    async fn hop(traceparent: Option<&str>) -> (String, String) {
+        let mut router = apply(Router::new().route(
+            "/",
+            get(|req: Request| async move {
+                let (trace_id, span_id, parent_span_id) = of(&req);
+                let header = req.headers()["traceparent"].to_str().unwrap().to_string();
+                format!("{} {} {} {}", trace_id, span_id, parent_span_id, header)
+            }),
+        ));
+        let mut req = Request::get("/");
+        if let Some(value) = traceparent {
+            req = req.header("traceparent", value);
+        }
+        let res = router.call(req.body(Body::empty()).unwrap()).await.unwrap();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        let (ids, header) = body.rsplit_once(' ').unwrap();
+        (ids.to_string(), header.to_string())
+    }
+
+    #[tokio::test]
+    async fn continues_the_caller_trace_with_a_new_span() {
+        let (ids, header) = hop(Some(&format!("00-{}-{}-01", TRACE, PARENT))).await;
+        let ids: Vec<&str> = ids.split(' ').collect();
+        assert_eq!(ids[0], TRACE);
+        assert_eq!(ids[2], PARENT);
+        assert_ne!(ids[1], PARENT);
+        assert_eq!(header, format!("00-{}-{}-01", TRACE, ids[1]));
+    }
+
+    #[tokio::test]
+    async fn starts_a_trace_without_a_parent() {
+        let (ids, header) = hop(Some("garbage")).await;
+        let ids: Vec<&str> = ids.split(' ').collect();
+        assert_eq!((ids[0].len(), ids[1].len(), ids[2]), (32, 16, ""));
+        assert_eq!(parse(&header), Some((ids[0], ids[1], "01")));
+    }
+}